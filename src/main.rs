@@ -18,6 +18,8 @@
 // Worst case: There is a single loop of  51+ length, which prevents any prisoner from surviving.
 // Best case: Biggest loop is < 50, so everyone finds their number.
 
+use std::thread;
+
 use clap::Parser;
 use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng};
@@ -28,19 +30,114 @@ fn simulate_prisoner_dilemma(
     prisoners: usize,
     iterations: u32,
     strategy: fn(&Vec<usize>, usize, Option<usize>) -> usize,
+    max_opens: usize,
+    threads: usize,
+    use_cycle_fast_path: bool,
 ) -> f32 {
-    // Iterations -> Vec<bool> representing successful/failed attempts
-    let simulation_results = (0..iterations).map(|_| {
-        // Shuffle boxes
-        let mut boxes = (0..prisoners).collect::<Vec<_>>();
-        boxes.shuffle(&mut thread_rng());
-
-        // Return result of applying strategy
-        apply_strategy(boxes, prisoners, strategy)
+    // When the caller opts into the fast path, the loop strategy's outcome is
+    // fully determined by the permutation's cycle structure, so we skip the
+    // per-prisoner chasing and decompose the cycles instead.
+
+    // Split the trials across workers; each owns its own RNG to avoid contention.
+    let threads = threads.max(1);
+    let successes: u32 = thread::scope(|scope| {
+        let handles = (0..threads)
+            .map(|worker| {
+                // Spread the remainder across the first few workers so the chunks
+                // differ by at most one trial.
+                let base = iterations / threads as u32;
+                let remainder = iterations % threads as u32;
+                let chunk = base + if (worker as u32) < remainder { 1 } else { 0 };
+
+                scope.spawn(move || {
+                    let mut rng = thread_rng();
+                    (0..chunk)
+                        .filter(|_| {
+                            let mut boxes = (0..prisoners).collect::<Vec<_>>();
+                            boxes.shuffle(&mut rng);
+
+                            if use_cycle_fast_path {
+                                longest_cycle_length(&boxes) <= max_opens
+                            } else {
+                                apply_strategy(boxes, prisoners, strategy, max_opens)
+                            }
+                        })
+                        .count() as u32
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
     });
 
     // Percentage of successful attempts 0.0 to 1.0
-    simulation_results.into_iter().filter(|s| *s).count() as f32 / iterations as f32
+    successes as f32 / iterations as f32
+}
+
+// Run `iterations` trials and tally how often each longest-cycle length occurs.
+// The returned vector is indexed by cycle length (0..=prisoners) and reveals why
+// the loop strategy succeeds as often as it does.
+fn longest_cycle_histogram(prisoners: usize, iterations: u32, threads: usize) -> Vec<u32> {
+    let threads = threads.max(1);
+
+    thread::scope(|scope| {
+        let handles = (0..threads)
+            .map(|worker| {
+                let base = iterations / threads as u32;
+                let remainder = iterations % threads as u32;
+                let chunk = base + if (worker as u32) < remainder { 1 } else { 0 };
+
+                scope.spawn(move || {
+                    let mut rng = thread_rng();
+                    let mut counts = vec![0u32; prisoners + 1];
+                    for _ in 0..chunk {
+                        let mut boxes = (0..prisoners).collect::<Vec<_>>();
+                        boxes.shuffle(&mut rng);
+                        counts[longest_cycle_length(&boxes)] += 1;
+                    }
+                    counts
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Merge the per-worker tallies into a single distribution.
+        handles
+            .into_iter()
+            .fold(vec![0u32; prisoners + 1], |mut acc, handle| {
+                for (length, count) in handle.join().unwrap().into_iter().enumerate() {
+                    acc[length] += count;
+                }
+                acc
+            })
+    })
+}
+
+// Decompose `boxes` into disjoint cycles in a single O(n) pass and return the
+// length of the longest one. For each unvisited index we follow j = boxes[j]
+// until we return to the start, counting and marking nodes along the way. The
+// loop strategy succeeds iff this value is at most the number of boxes each
+// prisoner may open (`prisoners / 2` by default).
+fn longest_cycle_length(boxes: &[usize]) -> usize {
+    let mut visited = vec![false; boxes.len()];
+    let mut longest = 0;
+
+    for start in 0..boxes.len() {
+        if visited[start] {
+            continue;
+        }
+
+        let mut length = 0;
+        let mut j = start;
+        while !visited[j] {
+            visited[j] = true;
+            j = boxes[j];
+            length += 1;
+        }
+
+        longest = longest.max(length);
+    }
+
+    longest
 }
 
 // Apply strategy to the given boxes and prisoners and return whether the strategy succeeded.
@@ -48,14 +145,20 @@ fn apply_strategy(
     boxes: Vec<usize>,
     prisoners: usize,
     strategy: fn(&Vec<usize>, usize, Option<usize>) -> usize,
+    max_opens: usize,
 ) -> bool {
     // Map Prisoner -> Success/Failed attempt using strategy
     let success_count = (0..prisoners)
         .map(|p| {
-            let mut opened_boxes = 0;
+            // The first selection already opens one box, so start the count at 1
+            // and let the prisoner open at most `max_opens` boxes in total.
+            if max_opens == 0 {
+                return false;
+            }
+            let mut opened_boxes = 1;
             let mut box_contents = strategy(&boxes, p, None);
 
-            while box_contents != p && opened_boxes < prisoners / 2 {
+            while box_contents != p && opened_boxes < max_opens {
                 opened_boxes += 1;
                 box_contents = strategy(&boxes, p, Some(box_contents));
             }
@@ -69,6 +172,30 @@ fn apply_strategy(
     success_count == prisoners
 }
 
+// Exact success probability of the optimal (loop) strategy when each prisoner
+// may open `max_opens` drawers: 1 - (H(n) - H(max_opens)), where H(m) is the
+// m-th harmonic number sum_{k=1}^{m} 1/k. For n = 100 and max_opens = 50 this
+// yields ~0.3118. The closed form is only exact when `max_opens >= n / 2`, since
+// it assumes at most one cycle can exceed the open limit.
+fn theoretical_optimal_probability(prisoners: usize, max_opens: usize) -> f64 {
+    // Opening more drawers than exist cannot help, so cap the limit at n.
+    1.0 - (harmonic(prisoners) - harmonic(max_opens.min(prisoners)))
+}
+
+// Success probability of the random strategy when each prisoner opens half the
+// drawers without repeats: each independently finds their number with
+// probability 1/2, so all n succeed together with probability (1/2)^n, which
+// vanishes to effectively 0 for large n. It does not account for
+// `naive_strategy` drawing with replacement or for a `max_opens` other than n / 2.
+fn theoretical_random_probability(prisoners: usize) -> f64 {
+    0.5_f64.powi(prisoners as i32)
+}
+
+// The m-th harmonic number sum_{k=1}^{m} 1/k.
+fn harmonic(m: usize) -> f64 {
+    (1..=m).map(|k| 1.0 / k as f64).sum()
+}
+
 // Loop strategy: Pick a box, then chose the box with that number in it
 fn loop_strategy(boxes: &Vec<usize>, prisoner: usize, previous: Option<usize>) -> usize {
     match previous {
@@ -78,21 +205,57 @@ fn loop_strategy(boxes: &Vec<usize>, prisoner: usize, previous: Option<usize>) -
 }
 
 // Naive strategy: Pick any box at random
-fn _naive_strategy(boxes: &Vec<usize>, _prisoner: usize, _previous: Option<usize>) -> usize {
+fn naive_strategy(boxes: &Vec<usize>, _prisoner: usize, _previous: Option<usize>) -> usize {
     boxes[rand::thread_rng().gen_range(0..boxes.len())]
 }
 
+// A strategy selected on the command line: its display label, evaluator,
+// closed-form probability, and whether the cycle fast path applies to it.
+type Selection = (
+    &'static str,
+    fn(&Vec<usize>, usize, Option<usize>) -> usize,
+    Option<f64>,
+    bool,
+);
+
+// Which strategy (or strategies) to simulate
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+enum Strategy {
+    /// Naive strategy: open boxes at random, up to the per-prisoner open limit
+    Random,
+    /// Loop strategy: follow the permutation's cycles
+    Optimal,
+    /// Run both strategies and compare them
+    Both,
+}
+
 // CLI Arguments
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
-    /// Name of the person to greet
+    /// Number of prisoners (and boxes) in the scenario
     #[clap(short, long, value_parser, default_value_t = 100)]
     prisoners: u32,
 
-    /// Number of times to greet
+    /// Number of trials to run
     #[clap(short, long, value_parser, default_value_t = 1000)]
     iterations: u32,
+
+    /// Which strategy to simulate: random, optimal, or both
+    #[clap(short, long, value_enum, default_value_t = Strategy::Both)]
+    strategy: Strategy,
+
+    /// Maximum number of worker threads (defaults to all available cores)
+    #[clap(short, long, value_parser)]
+    threads: Option<usize>,
+
+    /// Drawers each prisoner may open (defaults to half the prisoner count)
+    #[clap(short, long, value_parser)]
+    max_opens: Option<usize>,
+
+    /// Print the distribution of longest-cycle lengths across all trials
+    #[clap(long, value_parser, default_value_t = false)]
+    histogram: bool,
 }
 
 // Main function entry point
@@ -103,13 +266,113 @@ fn main() {
 
     let prisoners = args.prisoners as usize;
     let iterations = args.iterations;
+    let threads = args
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map(|n| n.get()).unwrap_or(1));
+    let max_opens = args.max_opens.unwrap_or(prisoners / 2);
 
     println!(
         "Prisoner's dilemma {} prisoners, {} times",
         prisoners, iterations
     );
 
-    let success_rate = simulate_prisoner_dilemma(prisoners, iterations, loop_strategy);
     println!("Prisoners: {}, Trials: {}", prisoners, iterations);
-    println!("Success rate: {}%", success_rate * 100.0);
+
+    // Collect the strategies selected on the command line, each paired with its
+    // evaluator and its closed-form probability.
+    let mut strategies: Vec<Selection> = Vec::new();
+    if args.strategy == Strategy::Random || args.strategy == Strategy::Both {
+        strategies.push((
+            "Random",
+            naive_strategy,
+            Some(theoretical_random_probability(prisoners)),
+            false,
+        ));
+    }
+    if args.strategy == Strategy::Optimal || args.strategy == Strategy::Both {
+        // The closed form is only exact for `max_opens >= n / 2`; below that the
+        // one-long-cycle assumption breaks down, so suppress the cell rather than
+        // print a number that looks analytic but isn't.
+        let theoretical = if max_opens >= prisoners / 2 {
+            Some(theoretical_optimal_probability(prisoners, max_opens))
+        } else {
+            None
+        };
+        strategies.push(("Optimal", loop_strategy, theoretical, true));
+    }
+
+    // Labeled table of simulated vs. theoretical success rates
+    println!("{:<10} {:>12} {:>12}", "Strategy", "Simulated", "Theoretical");
+    for (label, strategy, theoretical, fast_path) in strategies {
+        let success_rate = simulate_prisoner_dilemma(
+            prisoners, iterations, strategy, max_opens, threads, fast_path,
+        );
+        match theoretical {
+            Some(theoretical) => println!(
+                "{:<10} {:>11.4}% {:>11.4}%",
+                label,
+                success_rate * 100.0,
+                theoretical * 100.0
+            ),
+            None => println!("{:<10} {:>11.4}% {:>12}", label, success_rate * 100.0, "n/a"),
+        }
+    }
+
+    // Optionally expose the underlying permutation-cycle statistics.
+    if args.histogram {
+        let histogram = longest_cycle_histogram(prisoners, iterations, threads);
+        println!("\nLongest-cycle length distribution:");
+        for (length, count) in histogram.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+
+            // Trials whose longest cycle exceeds the open limit are the failures.
+            let marker = if length > max_opens { " <- failure" } else { "" };
+            println!("{:>4}: {:>8}{}", length, count, marker);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_cycle_length_handles_fixed_points_and_cycles() {
+        // Every box holds its own number: all cycles have length 1.
+        assert_eq!(longest_cycle_length(&[0, 1, 2, 3]), 1);
+        // A single cycle spanning every box.
+        assert_eq!(longest_cycle_length(&[1, 2, 3, 0]), 4);
+        // A 3-cycle (0 -> 1 -> 2 -> 0) alongside a fixed point.
+        assert_eq!(longest_cycle_length(&[1, 2, 0, 3]), 3);
+    }
+
+    #[test]
+    fn apply_strategy_opens_exactly_max_opens_boxes() {
+        // Longest cycle is 3; the loop strategy needs 3 opens to close it.
+        let boxes = vec![1, 2, 0];
+        assert!(apply_strategy(boxes.clone(), 3, loop_strategy, 3));
+        assert!(!apply_strategy(boxes, 3, loop_strategy, 2));
+    }
+
+    #[test]
+    fn loop_strategy_follows_the_permutation() {
+        let boxes = vec![2, 0, 1];
+        assert_eq!(loop_strategy(&boxes, 0, None), 2);
+        assert_eq!(loop_strategy(&boxes, 0, Some(2)), 1);
+    }
+
+    #[test]
+    fn naive_strategy_returns_an_existing_box_value() {
+        let boxes = vec![3, 1, 0, 2];
+        let value = naive_strategy(&boxes, 0, None);
+        assert!(boxes.contains(&value));
+    }
+
+    #[test]
+    fn theoretical_optimal_probability_matches_known_value() {
+        let p = theoretical_optimal_probability(100, 50);
+        assert!((p - 0.3118).abs() < 1e-3);
+    }
 }